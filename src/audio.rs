@@ -0,0 +1,202 @@
+//! Audio input sources and sample-rate conversion.
+//!
+//! The decoder only needs an `Iterator<Item = f32>` of samples at its
+//! working rate; this module provides the stdin source (the original
+//! `rtl_fm | alerter` pipe) and a TCP source for streaming demodulated
+//! audio from a remote host, e.g. a radio box running `rtl_fm` piped
+//! through `ncat`. The TCP source reconnects with backoff on EOF or a
+//! socket error instead of ending the iterator, so a transient upstream
+//! failure never tears down the `SameReceiver` state in the caller.
+//!
+//! Sources read raw samples in a configurable [`InputFormat`] and a
+//! [`Resampler`] adapts the capture rate to the decoder's working rate, so
+//! neither has to match `--rate` exactly.
+
+use byteorder::{NativeEndian, ReadBytesExt};
+use clap::ValueEnum;
+use std::io::{self, BufReader, Read};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Initial backoff between reconnect attempts to a network audio source.
+const RECONNECT_DELAY_MIN: Duration = Duration::from_secs(1);
+
+/// Cap on the reconnect backoff so a long outage still retries periodically.
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(60);
+
+/// Sample encoding of the raw input stream. Every variant is converted to
+/// an i16-scale `f32` so the decoder sees the same amplitude range
+/// regardless of the source format.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum InputFormat {
+    /// Signed 16-bit, native-endian (the original/default format).
+    S16,
+    /// Unsigned 8-bit, centered at 128.
+    U8,
+    /// 32-bit float, native-endian, normalized to [-1.0, 1.0].
+    F32,
+}
+
+impl InputFormat {
+    fn read_sample<R: Read>(&self, r: &mut R) -> io::Result<f32> {
+        match self {
+            InputFormat::S16 => r.read_i16::<NativeEndian>().map(|s| s as f32),
+            InputFormat::U8 => r.read_u8().map(|s| (s as f32 - 128.0) * 256.0),
+            InputFormat::F32 => r.read_f32::<NativeEndian>().map(|s| s * i16::MAX as f32),
+        }
+    }
+}
+
+/// Reads samples from stdin, as produced by a local `rtl_fm` pipe.
+pub struct StdinSource {
+    reader: BufReader<io::StdinLock<'static>>,
+    format: InputFormat,
+}
+
+impl StdinSource {
+    pub fn new(stdin: &'static io::Stdin, format: InputFormat) -> Self {
+        Self {
+            reader: BufReader::new(stdin.lock()),
+            format,
+        }
+    }
+}
+
+impl Iterator for StdinSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.format.read_sample(&mut self.reader).ok()
+    }
+}
+
+/// Reads samples from a remote `tcp://host:port` stream, reconnecting with
+/// a capped exponential backoff whenever the connection drops.
+pub struct TcpSource {
+    addr: String,
+    format: InputFormat,
+    reader: Option<BufReader<TcpStream>>,
+    /// Current reconnect backoff, doubled on each failed attempt and reset
+    /// to [`RECONNECT_DELAY_MIN`] as soon as a connection succeeds.
+    backoff: Duration,
+}
+
+impl TcpSource {
+    pub fn new(addr: String, format: InputFormat) -> Self {
+        Self {
+            addr,
+            format,
+            reader: None,
+            backoff: RECONNECT_DELAY_MIN,
+        }
+    }
+
+    fn ensure_connected(&mut self) {
+        while self.reader.is_none() {
+            match TcpStream::connect(&self.addr) {
+                Ok(stream) => {
+                    log::info!("Connected to audio source at {}", self.addr);
+                    self.reader = Some(BufReader::new(stream));
+                    self.backoff = RECONNECT_DELAY_MIN;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to connect to audio source {}: {}. Retrying in {:?}...",
+                        self.addr,
+                        e,
+                        self.backoff
+                    );
+                    std::thread::sleep(self.backoff);
+                    self.backoff = (self.backoff * 2).min(RECONNECT_DELAY_MAX);
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for TcpSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            self.ensure_connected();
+            match self.format.read_sample(self.reader.as_mut().unwrap()) {
+                Ok(sample) => return Some(sample),
+                Err(e) => {
+                    log::warn!(
+                        "Lost connection to audio source {}: {}. Reconnecting in {:?}...",
+                        self.addr,
+                        e,
+                        self.backoff
+                    );
+                    self.reader = None;
+                    std::thread::sleep(self.backoff);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the `host:port` part of a `tcp://host:port` `--source` argument,
+/// or `None` if the argument does not start with `tcp://`.
+pub fn parse_tcp_source(source: &str) -> Option<&str> {
+    source.strip_prefix("tcp://")
+}
+
+/// Linear-interpolating resampler from `input_rate` to `target_rate`.
+///
+/// Tracks a fractional read position `pos` that advances by
+/// `input_rate / target_rate` per output sample, interpolating between the
+/// two bracketing input samples by the fractional part. A small lookback
+/// buffer holds just enough input to keep the interpolation window
+/// available without re-reading the source.
+pub struct Resampler<I: Iterator<Item = f32>> {
+    input: I,
+    step: f64,
+    pos: f64,
+    buf: Vec<f32>,
+    base: usize,
+}
+
+impl<I: Iterator<Item = f32>> Resampler<I> {
+    pub fn new(input: I, input_rate: u32, target_rate: u32) -> Self {
+        Self {
+            input,
+            step: input_rate as f64 / target_rate as f64,
+            pos: 0.0,
+            buf: Vec::new(),
+            base: 0,
+        }
+    }
+
+    /// Returns the input sample at absolute index `idx`, pulling more
+    /// samples from the source as needed.
+    fn sample_at(&mut self, idx: usize) -> Option<f32> {
+        while self.base + self.buf.len() <= idx {
+            self.buf.push(self.input.next()?);
+        }
+        self.buf.get(idx - self.base).copied()
+    }
+}
+
+impl<I: Iterator<Item = f32>> Iterator for Resampler<I> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let idx = self.pos.floor() as usize;
+        let frac = (self.pos - idx as f64) as f32;
+        let s0 = self.sample_at(idx)?;
+        let s1 = self.sample_at(idx + 1).unwrap_or(s0);
+
+        self.pos += self.step;
+
+        // Drop samples we'll never interpolate against again.
+        if idx > self.base {
+            let drop = idx - self.base;
+            self.buf.drain(0..drop);
+            self.base += drop;
+        }
+
+        Some(s0 + (s1 - s0) * frac)
+    }
+}
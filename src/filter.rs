@@ -0,0 +1,69 @@
+//! Combinable alert filters: location (handled inline where the resolved
+//! codes are available), event type, and minimum significance. Each is
+//! independently optional, parsed once at startup so the decode loop only
+//! does set lookups per message.
+
+use anyhow::{anyhow, Result};
+use sameold::SignificanceLevel;
+use std::collections::HashSet;
+
+use crate::Args;
+
+/// Event-type and significance filters, parsed once from [`Args`].
+pub struct Filters {
+    events: Option<HashSet<String>>,
+    min_significance: Option<SignificanceLevel>,
+}
+
+impl Filters {
+    pub fn from_args(args: &Args) -> Result<Self> {
+        let events = if args.events.is_empty() {
+            None
+        } else {
+            Some(args.events.iter().map(|e| e.to_uppercase()).collect())
+        };
+
+        let min_significance = args
+            .min_significance
+            .as_deref()
+            .map(parse_significance)
+            .transpose()?;
+
+        Ok(Self {
+            events,
+            min_significance,
+        })
+    }
+
+    /// Whether `event_code` (e.g. "TOR") passes the `--events` filter.
+    pub fn event_allowed(&self, event_code: &str) -> bool {
+        match &self.events {
+            Some(events) => events.contains(event_code),
+            None => true,
+        }
+    }
+
+    /// Whether `level` meets the `--min-significance` filter.
+    pub fn significance_allowed(&self, level: SignificanceLevel) -> bool {
+        match self.min_significance {
+            // `SignificanceLevel` derives `Ord` least-to-most significant,
+            // so this is already the comparison we want.
+            Some(min) => level >= min,
+            None => true,
+        }
+    }
+}
+
+fn parse_significance(s: &str) -> Result<SignificanceLevel> {
+    match s.to_lowercase().as_str() {
+        "test" => Ok(SignificanceLevel::Test),
+        "statement" => Ok(SignificanceLevel::Statement),
+        "watch" => Ok(SignificanceLevel::Watch),
+        "warning" => Ok(SignificanceLevel::Warning),
+        "emergency" => Ok(SignificanceLevel::Emergency),
+        other => Err(anyhow!(
+            "invalid --min-significance '{}': expected one of Test, Statement, Watch, Warning, Emergency",
+            other
+        )),
+    }
+}
@@ -1,5 +1,4 @@
 use anyhow::Result;
-use byteorder::{NativeEndian, ReadBytesExt};
 use csv::ReaderBuilder;
 use log::{info, LevelFilter, log};
 use rust_embed::RustEmbed;
@@ -10,10 +9,22 @@ use simple_logger::SimpleLogger;
 use std::collections::HashMap;
 use std::env::args;
 use std::io::{self};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::time::sleep;
-use std::process::{Command, Stdio};
 use strum::EnumMessage;
+use async_trait::async_trait;
+
+mod transport;
+use transport::MeshtasticConnection;
+mod sink;
+use sink::{AlertEvent, AlertSink};
+mod mqtt;
+use mqtt::MqttSink;
+mod audio;
+mod filter;
+use filter::Filters;
+mod queue;
+use queue::AlertQueue;
 
 #[derive(RustEmbed)]
 #[folder = "src"]
@@ -27,14 +38,16 @@ struct Record {
 }
 
 struct MessageSender {
-    last_message_time: Option<Instant>,
+    connection: MeshtasticConnection,
 }
 
 impl MessageSender {
-    fn new() -> Self {
-        MessageSender {
-            last_message_time: None,
-        }
+    /// Opens the persistent node connection that will be reused for every
+    /// message sent over the lifetime of the program.
+    async fn new(args: &Args) -> Result<Self> {
+        Ok(MessageSender {
+            connection: MeshtasticConnection::connect(args).await?,
+        })
     }
 
     async fn send_message_with_retry(
@@ -43,41 +56,13 @@ impl MessageSender {
         message: &str,
         retries: u32,
         delay: Duration,
-        args: Args,
     ) -> Result<(), String> {
-        // Ensure at least 20 seconds between messages
-        if let Some(last_time) = self.last_message_time {
-            let elapsed = last_time.elapsed();
-            if elapsed < Duration::from_secs(20) {
-                sleep(Duration::from_secs(20) - elapsed).await;
-            }
-        }
-
+        // The 20-second inter-message pacing now lives in the outbound
+        // alert queue's drain rate, not here, so a multi-chunk message
+        // never blocks whatever is feeding this sender.
         for attempt in 0..=retries {
-            // Create a new Command instance
-            let mut command = Command::new("meshtastic");
-                command.arg("--ch-index");
-                command.arg(chan.to_string());
-                command.arg("--sendtext");
-                command.arg(message.to_string()); // Convert message to String to extend its lifetime
-                command.arg("--ack");
-
-            // Conditionally add the host argument if provided
-            if let Some(host) = &args.host {
-                command.arg("--host").arg(host);
-            }
-            
-            // Conditionally add the port argument if provided
-            if let Some(port) = &args.port {
-                command.arg("--port").arg(port);
-            }
-
-            // Execute the command
-            let result = command.spawn();
-
-            match result {
-                Ok(_) => {
-                    self.last_message_time = Some(Instant::now());
+            match self.connection.send_text_with_ack(chan, message).await {
+                Ok(()) => {
                     return Ok(());
                 }
                 Err(e) => {
@@ -93,72 +78,46 @@ impl MessageSender {
         }
         Ok(())
     }
-
-
-
 }
 
-async fn check_node_connection(args: Args) -> Result<()> {
-    // Construct the command to run `meshtastic --info`
-    let mut cmd = Command::new("meshtastic");
-
-
-
-    // Conditionally add the "--host" argument if the host is provided
-    if let Some(host) = &args.host {
-        cmd.arg("--host");
-        cmd.arg(host);  // Add host argument here
-    }
-
-    // Conditionally add the "--port" argument if the serial port is provided ie. /dev/ttyUSB0
-    if let Some(port) = &args.port {
-        cmd.arg("--port");
-        cmd.arg(port);  // Add port argument here
-    }
-
-
-    // Add the --info argument
-    cmd.arg("--info");
-
-    // Ensure the command doesn't output to the console
-    cmd.stdout(Stdio::piped());
-
-    // Run the command and capture the output
-    let output = cmd.output();
-
-    match output {
-        Ok(output) => {
-            // Convert the stdout to a string (output is captured as bytes)
-            let stdout = String::from_utf8_lossy(&output.stdout);
-
-            // Check if the output contains "Error"
-            if stdout.contains("Error") {
-                log::error!("Received error output: {}", stdout);
-                std::process::exit(1);
-            }
-
-
-            // Check the first line of the output
-            if let Some(first_line) = stdout.lines().next() {
-                if first_line == "Connected to radio" {
-                    log::info!("Successfully connected to the node.");
-                    return Ok(());
-                } else {
-                    log::error!("Failed to connect to the radio. First line: {}", first_line);
-                    std::process::exit(1);
-                }
+#[async_trait]
+impl AlertSink for MessageSender {
+    /// Splits `message` at spaces into chunks of at most 75 bytes and sends
+    /// each over the mesh on `channel`, retrying as configured. The last
+    /// chunk is everything after the final split and is not capped at 75
+    /// bytes. `event` is unused here; it exists for sinks (like MQTT)
+    /// that publish the structured form instead of the rendered text.
+    async fn send_alert(&mut self, channel: u32, message: &str, _event: &AlertEvent) -> Result<()> {
+        let mut myvec: Vec<usize> = message
+            .bytes()
+            .enumerate()
+            .filter(|(_, c)| *c == b' ')
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let mut curpos: usize = 0;
+        let mut curlen: usize = 0;
+        let mut startpos: usize = 0;
+        for i in myvec.iter_mut() {
+            if curlen + *i - curpos > 75 {
+                self.send_message_with_retry(channel, &message[startpos..(startpos + curlen)], 3, Duration::from_secs(5))
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                curpos = startpos + curlen;
+                startpos += curlen;
+                curlen = 0;
             } else {
-                log::error!("Output from meshtastic --info was empty.");
-                std::process::exit(1);
+                curlen += *i - curpos;
+                curpos = *i;
             }
         }
-        Err(e) => {
-            // Log error if the command failed to run
-            log::error!("Failed to execute meshtastic --info: {}", e);
-            std::process::exit(1);
+        curlen = message.len() - startpos;
+        if curlen != 0 {
+            self.send_message_with_retry(channel, &message[startpos..(startpos + curlen)], 3, Duration::from_secs(5))
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
         }
+        Ok(())
     }
-
 }
 
 async fn load_csv_into_hashmap() -> HashMap<String, (String, String)> {
@@ -202,6 +161,10 @@ struct Args {
     #[arg(long)]
     host: Option<String>,
 
+    /// Serial port of device to connect to, e.g. /dev/ttyUSB0. Ignored if --host is provided
+    #[arg(long)]
+    port: Option<String>,
+
     /// Sample rate.
     #[arg(long, short, default_value_t = 48000)]
     rate: u32,
@@ -210,6 +173,53 @@ struct Args {
     #[arg(long, short, value_delimiter = ',', default_value = None, required = false)]
     locations: Vec<String>,
 
+    /// MQTT broker host to publish alerts to, in addition to the mesh. If
+    /// omitted, no MQTT sink is created
+    #[arg(long)]
+    mqtt_host: Option<String>,
+
+    /// MQTT broker port
+    #[arg(long, default_value_t = 1883)]
+    mqtt_port: u16,
+
+    /// MQTT topic alerts are published to. `{channel}` is replaced with the
+    /// alert/test channel number
+    #[arg(long, default_value = "msh/2/json/eas/{channel}")]
+    mqtt_topic: String,
+
+    /// Username for MQTT broker authentication, if required
+    #[arg(long)]
+    mqtt_user: Option<String>,
+
+    /// Password for MQTT broker authentication, if required
+    #[arg(long)]
+    mqtt_pass: Option<String>,
+
+    /// Audio input source: `stdin` (default, for a local `rtl_fm` pipe) or
+    /// `tcp://host:port` to read from a remote demodulated-audio stream
+    #[arg(long, default_value = "stdin")]
+    source: String,
+
+    /// Sample encoding of the input stream
+    #[arg(long, value_enum, default_value = "s16")]
+    input_format: audio::InputFormat,
+
+    /// Sample rate of the input stream. Samples are resampled internally to
+    /// the decoder's working rate (--rate); this default does not follow --rate
+    #[arg(long, default_value_t = 48000)]
+    input_rate: u32,
+
+    /// Event codes that must be present to send an alert, e.g. TOR,SVR,FFW
+    #[arg(long, value_delimiter = ',', default_value = None, required = false)]
+    events: Vec<String>,
+
+    /// Minimum significance level required to send an alert, e.g. Warning
+    #[arg(long)]
+    min_significance: Option<String>,
+
+    /// File used to persist not-yet-sent alerts across restarts
+    #[arg(long, default_value = "pending_alerts.jsonl")]
+    queue_file: std::path::PathBuf,
 }
 
 #[tokio::main]
@@ -252,7 +262,18 @@ async fn main() -> Result<()> {
         }
     }
 
-    check_node_connection(Args::parse()).await.expect("Failed to check node connection");
+    let filters = Filters::from_args(&args)?;
+
+    let sender = MessageSender::new(&args)
+        .await
+        .expect("Failed to connect to Meshtastic node");
+
+    let mut sinks: Vec<Box<dyn AlertSink>> = vec![Box::new(sender)];
+    if let Some(mqtt_sink) = MqttSink::connect(&args).expect("Failed to configure MQTT sink") {
+        log::info!("Publishing alerts to MQTT at {}", args.mqtt_host.as_deref().unwrap_or_default());
+        sinks.push(Box::new(mqtt_sink));
+    }
+    let queue = AlertQueue::start(args.queue_file.clone(), sinks);
 
     // Create a SameReceiver.
     let mut rx = SameReceiverBuilder::new(args.rate)
@@ -262,24 +283,27 @@ async fn main() -> Result<()> {
         .with_preamble_max_errors(2) // bit error limit when detecting sync sequence
         .build();
 
-    // Set up stdin as the input source
-    let stdin = io::stdin();
-    // Check if there is any input from stdin
-    if atty::is(atty::Stream::Stdin) {
-        log::error!("Error: No input provided to stdin. Please provide RTL FM input.");
-        std::process::exit(1);
-    }
-
     let map = load_csv_into_hashmap().await;
     log::info!("Loaded locations CSV");
 
-    let stdin_handle = stdin.lock();
-    let mut inbuf = Box::new(io::BufReader::new(stdin_handle));
-
-    // Create an iterator for audio source from stdin, reading i16 and converting to f32
-    let audiosrc = std::iter::from_fn(|| inbuf.read_i16::<NativeEndian>().ok());
+    // Set up the audio input source: a remote tcp:// stream, reconnecting
+    // automatically, or stdin for a local `rtl_fm` pipe.
+    let audiosrc: Box<dyn Iterator<Item = f32>> = if let Some(tcp_addr) = audio::parse_tcp_source(&args.source) {
+        log::info!("Reading audio from {}", args.source);
+        Box::new(audio::TcpSource::new(tcp_addr.to_string(), args.input_format))
+    } else {
+        if atty::is(atty::Stream::Stdin) {
+            log::error!("Error: No input provided to stdin. Please provide RTL FM input, or use --source tcp://host:port.");
+            std::process::exit(1);
+        }
+        let stdin: &'static io::Stdin = Box::leak(Box::new(io::stdin()));
+        Box::new(audio::StdinSource::new(stdin, args.input_format))
+    };
 
-    let mut sender = MessageSender::new();
+    // Resample from the capture rate to the decoder's working rate so a
+    // mismatched --input-rate no longer silently prevents SAME framing
+    // from locking.
+    let audiosrc = audio::Resampler::new(audiosrc, args.input_rate, args.rate);
 
     log::info!("Monitoring for alerts");
     log::info!("Alerts will be sent to channel: {}", alert_channel);
@@ -290,11 +314,28 @@ async fn main() -> Result<()> {
     }
 
     // Process messages from the audio source
-    for msg in rx.iter_messages(audiosrc.map(|sa| sa as f32)) {
+    for msg in rx.iter_messages(audiosrc) {
         match msg {
             Message::StartOfMessage(hdr) => {
                 let evt = hdr.event();
                 log::info!("Begin SAME voice message: {:?}", hdr);
+
+                // `evt.to_string()` is the human-readable phrase (e.g. "Tornado
+                // Warning"), used for the rendered message below; the filter
+                // needs the 3-letter SAME code instead.
+                let event_code = evt.as_str();
+                if !filters.event_allowed(event_code) {
+                    log::info!("Ignoring alert suppressed by event filter (event={})", event_code);
+                    continue;
+                }
+                if !filters.significance_allowed(evt.significance()) {
+                    log::info!(
+                        "Ignoring alert suppressed by significance filter (significance={:?})",
+                        evt.significance()
+                    );
+                    continue;
+                }
+
                 let mut message: String;
                 let mut send_channel: u32 = alert_channel;
 
@@ -330,6 +371,8 @@ async fn main() -> Result<()> {
                     }
                 }
                 let codes: Vec<String> = hdr.location_str_iter().map(|s| s.to_string()).collect();
+                let raw_codes = codes.clone();
+                let mut locations_found: Vec<String> = Vec::new();
 
                 if hdr.is_national() {
                     message += " Nationwide Alert"
@@ -355,8 +398,6 @@ async fn main() -> Result<()> {
                         log::info!("No location filter applied (locations empty) or no locations in alert");
                     }
 
-                    let mut locations_found = Vec::new();
-
                     // Pass each code into the function and collect the results
                     for code in codes {
                         if let Some((county, _state)) =
@@ -397,32 +438,18 @@ async fn main() -> Result<()> {
                     }
                 }
 
-                log::info!("Attempting to send message over the mesh: {}", message);
-
-                // Split and send the message in chunks of 75 characters, using retry logic
-                let mut myvec: Vec<usize> = message.bytes().enumerate().filter(|(_,c)| *c == b' ').map(|(i,_)| i).collect::<Vec<_>>();
-                let mut curpos: usize = 0;
-                let mut curlen: usize = 0;
-                let mut startpos: usize = 0;
-                for i in myvec.iter_mut() {
-                    if curlen + *i - curpos > 75 {
-                        sender
-                            .send_message_with_retry(send_channel, &message[startpos..(startpos + curlen)], 3, Duration::from_secs(5), Args::parse())
-                            .await.expect("Failed sending msg");
-                        curpos = startpos + curlen;
-                        startpos += curlen;
-                        curlen = 0;
-                    } else {
-                        curlen += *i - curpos;
-                        curpos = *i;
-                    }
-                }
-                curlen = message.len() - startpos;
-                if curlen != 0 {
-                    sender
-                        .send_message_with_retry(send_channel, &message[startpos..(startpos + curlen)], 3, Duration::from_secs(5), Args::parse())
-                        .await.expect("Failed sending msg");
-                }
+                log::info!("Queuing message for delivery: {}", message);
+
+                let event = AlertEvent {
+                    event_name: evt.to_string(),
+                    significance: format!("{:?}", evt.significance()),
+                    originator: hdr.originator().get_detailed_message().unwrap_or_default().to_string(),
+                    callsign: hdr.callsign().to_string(),
+                    locations: locations_found,
+                    raw_codes,
+                };
+
+                queue.enqueue(send_channel, message, event);
             }
             Message::EndOfMessage => {
                 log::info!("End SAME voice message");
@@ -0,0 +1,111 @@
+//! MQTT output sink.
+//!
+//! Publishes a JSON payload for every alert to a configured broker so that
+//! Home Assistant / Node-RED style automations can react to EAS events
+//! without needing a Meshtastic node in the loop. Connection handling is a
+//! small reconnect loop driving `rumqttc`'s event loop in the background;
+//! publishing never blocks the audio-decode loop, and if the broker is
+//! unreachable only the most recent few alerts are retained.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+
+use crate::sink::{AlertEvent, AlertSink};
+use crate::Args;
+
+/// Number of unpublished alerts retained while the broker is unreachable;
+/// older ones are dropped rather than delaying the audio-decode loop.
+const MAX_QUEUED_ALERTS: usize = 5;
+
+pub struct MqttSink {
+    tx: mpsc::UnboundedSender<(String, String)>,
+    /// Topic template; any `{channel}` placeholder is replaced with the
+    /// alert/test channel number at publish time.
+    topic_template: String,
+}
+
+impl MqttSink {
+    /// Connects to the broker described by `args`, or returns `None` if no
+    /// `--mqtt-host` was given.
+    pub fn connect(args: &Args) -> Result<Option<Self>> {
+        let Some(host) = args.mqtt_host.clone() else {
+            return Ok(None);
+        };
+        let topic_template = args.mqtt_topic.clone();
+
+        let mut options = MqttOptions::new("meshtastic-same-eas-alerter", host, args.mqtt_port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let (Some(user), Some(pass)) = (&args.mqtt_user, &args.mqtt_pass) {
+            options.set_credentials(user, pass);
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(options, 10);
+        let (tx, mut rx) = mpsc::unbounded_channel::<(String, String)>();
+
+        tokio::spawn(async move {
+            let mut pending: VecDeque<(String, String)> = VecDeque::new();
+            // Alerts are handed to the client only while connected, so an
+            // outage leaves them in the bounded `pending` queue.
+            let mut connected = false;
+            loop {
+                tokio::select! {
+                    received = rx.recv() => {
+                        match received {
+                            Some(item) => {
+                                if pending.len() >= MAX_QUEUED_ALERTS {
+                                    pending.pop_front();
+                                    log::warn!("MQTT broker unreachable; dropping oldest queued alert");
+                                }
+                                pending.push_back(item);
+                            }
+                            None => break,
+                        }
+                    }
+                    event = eventloop.poll() => {
+                        match event {
+                            Ok(Event::Incoming(Packet::ConnAck(_))) => connected = true,
+                            Ok(_) => {}
+                            Err(e) => {
+                                connected = false;
+                                log::warn!("MQTT connection error: {}. Reconnecting...", e);
+                                sleep(Duration::from_secs(5)).await;
+                                continue;
+                            }
+                        }
+                    }
+                }
+
+                if !connected {
+                    continue;
+                }
+
+                while let Some((topic, payload)) = pending.pop_front() {
+                    if let Err(e) = client.try_publish(&topic, QoS::AtLeastOnce, false, payload.clone()) {
+                        log::warn!("Failed to publish alert to MQTT topic {}: {}", topic, e);
+                        pending.push_front((topic, payload));
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Some(Self { tx, topic_template }))
+    }
+}
+
+#[async_trait]
+impl AlertSink for MqttSink {
+    async fn send_alert(&mut self, channel: u32, _message: &str, event: &AlertEvent) -> Result<()> {
+        let topic = self.topic_template.replace("{channel}", &channel.to_string());
+        let payload = serde_json::to_string(event)?;
+        // The background task owns reconnection and queue bounding; a send
+        // error here just means that task has exited.
+        let _ = self.tx.send((topic, payload));
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Persistent, deduplicating outbound alert queue.
+//!
+//! SAME messages are transmitted three times and stations often re-issue
+//! the same event, and sending over the mesh takes 20+ seconds per message
+//! in 75-char chunks. This queue decouples sending from decoding: the
+//! decode loop only ever does a non-blocking enqueue, a background task
+//! drains one alert at a time at a fixed rate, and alerts still waiting
+//! to be sent are persisted to disk so a crash doesn't lose them. An alert
+//! is removed from the file when its send starts; if a sink fails to
+//! deliver it, the error is logged and the queue does not send it again.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+
+use crate::sink::{AlertEvent, AlertSink};
+
+/// Minimum time between outbound sends. This used to be an inline `sleep`
+/// in the sender, blocking the decode loop; it is now just the rate at
+/// which this queue drains.
+const DRAIN_INTERVAL: Duration = Duration::from_secs(20);
+
+/// How long a dedup key is remembered. SAME headers don't expose a decoded
+/// purge timestamp we can rely on, so this is a fixed window sized to the
+/// SAME protocol's typical purge durations (15 minutes to a few hours).
+const DEFAULT_VALID_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedAlert {
+    dedup_key: String,
+    channel: u32,
+    message: String,
+    event: AlertEvent,
+    /// Unix timestamp after which this alert is no longer worth sending.
+    valid_until: u64,
+}
+
+/// Handle for enqueueing alerts; the actual sending happens in a
+/// background task owned by [`AlertQueue::start`].
+#[derive(Clone)]
+pub struct AlertQueue {
+    tx: mpsc::UnboundedSender<QueuedAlert>,
+}
+
+impl AlertQueue {
+    /// Loads any still-valid alerts persisted from a previous run, then
+    /// spawns the background task that dedups, persists, and drains the
+    /// queue through `sinks`.
+    pub fn start(path: PathBuf, sinks: Vec<Box<dyn AlertSink>>) -> Self {
+        let now = unix_now();
+        let mut pending = load_pending(&path, now);
+        let mut seen: HashMap<String, u64> =
+            pending.iter().map(|a| (a.dedup_key.clone(), a.valid_until)).collect();
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<QueuedAlert>();
+
+        tokio::spawn(async move {
+            let mut sinks = sinks;
+            loop {
+                if pending.is_empty() {
+                    match rx.recv().await {
+                        Some(alert) => enqueue(alert, &mut pending, &mut seen, &path),
+                        None => break,
+                    }
+                    continue;
+                }
+
+                let alert = pending.pop_front().unwrap();
+                save_pending(&path, &pending);
+
+                for sink in sinks.iter_mut() {
+                    if let Err(e) = sink.send_alert(alert.channel, &alert.message, &alert.event).await {
+                        log::error!("Failed sending queued alert: {}", e);
+                    }
+                }
+
+                // Pick up anything that arrived while we were sending
+                // without blocking the drain rate below.
+                while let Ok(alert) = rx.try_recv() {
+                    enqueue(alert, &mut pending, &mut seen, &path);
+                }
+
+                sleep(DRAIN_INTERVAL).await;
+            }
+        });
+
+        Self { tx }
+    }
+
+    /// Queues `event` for delivery, computing its dedup key from the event
+    /// name (the human-readable phrase, not the three-letter SAME code),
+    /// originator, and sorted location codes so repeated
+    /// transmissions of the same alert are only sent once; whether a
+    /// station re-issuing the same event after its valid window has passed
+    /// is mistaken for a duplicate is decided separately, by `valid_until`.
+    /// Never blocks the caller.
+    pub fn enqueue(&self, channel: u32, message: String, event: AlertEvent) {
+        let valid_until = unix_now() + DEFAULT_VALID_WINDOW.as_secs();
+
+        let mut codes = event.raw_codes.clone();
+        codes.sort();
+        // The key is content-only: whether a matching alert is a duplicate
+        // or a fresh re-issuance is entirely decided by `valid_until` below,
+        // via the `seen` map in the free-standing `enqueue` (pruned once an
+        // entry's window has passed). Folding a wall-clock bucket into the
+        // key itself would let two transmissions of the same alert landing
+        // on opposite sides of a bucket boundary be treated as distinct.
+        let dedup_key = format!(
+            "{}|{}|{}",
+            event.event_name,
+            event.originator,
+            codes.join(",")
+        );
+
+        let alert = QueuedAlert {
+            dedup_key,
+            channel,
+            message,
+            event,
+            valid_until,
+        };
+
+        if self.tx.send(alert).is_err() {
+            log::error!("Alert queue worker has stopped; dropping alert");
+        }
+    }
+}
+
+fn enqueue(
+    alert: QueuedAlert,
+    pending: &mut VecDeque<QueuedAlert>,
+    seen: &mut HashMap<String, u64>,
+    path: &PathBuf,
+) {
+    let now = unix_now();
+    // Prune dedup entries whose window has already passed so `seen` doesn't
+    // grow unbounded over a long-running monitor.
+    seen.retain(|_, valid_until| *valid_until >= now);
+
+    if let Some(&valid_until) = seen.get(&alert.dedup_key) {
+        if valid_until >= now {
+            log::info!("Dropping duplicate alert (key={})", alert.dedup_key);
+            return;
+        }
+    }
+
+    seen.insert(alert.dedup_key.clone(), alert.valid_until);
+    pending.push_back(alert);
+    save_pending(path, pending);
+}
+
+fn load_pending(path: &PathBuf, now: u64) -> VecDeque<QueuedAlert> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return VecDeque::new();
+    };
+
+    let mut loaded = VecDeque::new();
+    for line in contents.lines() {
+        match serde_json::from_str::<QueuedAlert>(line) {
+            Ok(alert) if alert.valid_until >= now => loaded.push_back(alert),
+            Ok(alert) => log::info!("Discarding expired queued alert (key={})", alert.dedup_key),
+            Err(e) => log::warn!("Skipping malformed queued alert: {}", e),
+        }
+    }
+
+    if !loaded.is_empty() {
+        log::info!("Replaying {} queued alert(s) from {}", loaded.len(), path.display());
+    }
+
+    loaded
+}
+
+fn save_pending(path: &PathBuf, pending: &VecDeque<QueuedAlert>) {
+    let result: Result<()> = (|| {
+        let mut contents = String::new();
+        for alert in pending {
+            contents.push_str(&serde_json::to_string(alert)?);
+            contents.push('\n');
+        }
+        std::fs::write(path, contents)?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        log::error!("Failed to persist outbound alert queue to {}: {}", path.display(), e);
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
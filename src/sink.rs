@@ -0,0 +1,33 @@
+//! Output sinks for decoded SAME alerts.
+//!
+//! The Meshtastic radio is one sink among potentially several; each output
+//! implements [`AlertSink`] and is invoked once per alert so new outputs
+//! (MQTT, logging, etc.) can be added without touching the decode loop.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Structured view of a decoded alert, independent of how any particular
+/// sink chooses to render or transmit it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub event_name: String,
+    pub significance: String,
+    pub originator: String,
+    pub callsign: String,
+    /// Resolved county descriptions, e.g. "Northwest Travis"; the state is
+    /// not included.
+    pub locations: Vec<String>,
+    /// Raw SAME location codes as received, e.g. "048453".
+    pub raw_codes: Vec<String>,
+}
+
+/// Something a decoded alert can be delivered to.
+#[async_trait]
+pub trait AlertSink {
+    /// Delivers `event` (rendered as `message` for text-oriented sinks) on
+    /// `channel`. `channel` is the Meshtastic alert/test channel the alert
+    /// was routed to; sinks that have no notion of channels may ignore it.
+    async fn send_alert(&mut self, channel: u32, message: &str, event: &AlertEvent) -> Result<()>;
+}
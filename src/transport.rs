@@ -0,0 +1,156 @@
+//! Native Meshtastic protobuf transport.
+//!
+//! Replaces the old approach of shelling out to the `meshtastic` Python CLI
+//! for every chunk with a single persistent connection (serial or TCP) to
+//! the node, speaking the protobuf `FromRadio`/`ToRadio` API directly via
+//! the `meshtastic` crate's `StreamApi`.
+
+use anyhow::{anyhow, Context, Result};
+use meshtastic::api::{ConnectedStreamApi, StreamApi};
+use meshtastic::packet::{PacketDestination, PacketRouter};
+use meshtastic::protobufs::{from_radio, mesh_packet, routing, PortNum, Routing};
+use meshtastic::types::{NodeId, PacketId};
+use meshtastic::utils;
+use prost::Message as _;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::Args;
+
+/// Ignores every packet it is handed, our outbound text packets included;
+/// the `StreamApi` requires a `PacketRouter` impl to hand packets to even
+/// when we have no mesh-wide routing logic of our own.
+struct NoopPacketRouter;
+
+impl PacketRouter<(), String> for NoopPacketRouter {
+    fn handle_packet_from_radio(&mut self, _packet: meshtastic::protobufs::FromRadio) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn handle_mesh_packet(&mut self, _packet: meshtastic::protobufs::MeshPacket) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn source_node_id(&self) -> NodeId {
+        NodeId::new(0)
+    }
+}
+
+/// A live connection to a Meshtastic node, opened once and reused for the
+/// lifetime of the program instead of being re-established per message.
+pub struct MeshtasticConnection {
+    api: ConnectedStreamApi,
+    decoded: UnboundedReceiver<meshtastic::protobufs::FromRadio>,
+    router: NoopPacketRouter,
+}
+
+impl MeshtasticConnection {
+    /// Opens a connection to the node described by `args`, preferring a TCP
+    /// host when one is given and falling back to a serial port.
+    pub async fn connect(args: &Args) -> Result<Self> {
+        let stream_api = StreamApi::new();
+
+        let (decoded, stream_api) = if let Some(host) = &args.host {
+            let stream = utils::stream::build_tcp_stream(host.clone())
+                .await
+                .with_context(|| format!("connecting to Meshtastic node at {}", host))?;
+            stream_api.connect(stream).await
+        } else if let Some(port) = &args.port {
+            let stream = utils::stream::build_serial_stream(port.clone(), None, None, None)
+                .with_context(|| format!("opening serial port {}", port))?;
+            stream_api.connect(stream).await
+        } else {
+            return Err(anyhow!(
+                "no node address provided; pass --host for TCP or --port for serial"
+            ));
+        };
+
+        let config_id = utils::generate_rand_id();
+        let mut stream_api = stream_api.configure(config_id).await?;
+        let mut decoded = decoded;
+
+        // The first packets off the wire are the config handshake; pull
+        // packets until we see our own node info so we know who we are.
+        let my_node_info = loop {
+            match decoded.recv().await {
+                Some(packet) => {
+                    if let Some(from_radio::PayloadVariant::MyInfo(info)) = packet.payload_variant {
+                        break info;
+                    }
+                }
+                None => return Err(anyhow!("node closed the connection during handshake")),
+            }
+        };
+
+        log::info!(
+            "Connected to node {:#x} over {}",
+            my_node_info.my_node_num,
+            if args.host.is_some() { "TCP" } else { "serial" }
+        );
+
+        Ok(Self {
+            api: stream_api,
+            decoded,
+            router: NoopPacketRouter,
+        })
+    }
+
+    /// Sends `text` on `channel` and waits for the node to acknowledge
+    /// delivery, returning an error if the ack is not observed.
+    pub async fn send_text_with_ack(&mut self, channel: u32, text: &str) -> Result<()> {
+        let packet_id = self
+            .api
+            .send_text(
+                &mut self.router,
+                text.to_string(),
+                PacketDestination::Broadcast,
+                true,
+                channel,
+            )
+            .await
+            .map_err(|e| anyhow!("sending text packet: {e}"))?;
+
+        self.await_ack(packet_id).await
+    }
+
+    async fn await_ack(&mut self, packet_id: PacketId) -> Result<()> {
+        use std::time::Duration;
+        use tokio::time::timeout;
+
+        // A delivery ack is a separate Routing-app packet carrying the
+        // original packet's id as its `request_id`, not a packet whose own
+        // `id` matches what we sent. The packet's `error_reason` still has
+        // to be checked, though: a NAK (e.g. `MAX_RETRANSMIT`, `NO_CHANNEL`)
+        // is delivered as a Routing packet with the same `request_id`, so
+        // matching on `request_id` alone would report failed deliveries as
+        // successful acks.
+        let wait = timeout(Duration::from_secs(10), async {
+            while let Some(packet) = self.decoded.recv().await {
+                if let Some(from_radio::PayloadVariant::Packet(mesh_packet)) = packet.payload_variant {
+                    if let Some(mesh_packet::PayloadVariant::Decoded(data)) = mesh_packet.payload_variant {
+                        if data.request_id == packet_id && data.portnum() == PortNum::RoutingApp {
+                            let error = Routing::decode(data.payload.as_slice())
+                                .ok()
+                                .and_then(|routing| match routing.variant {
+                                    Some(routing::Variant::ErrorReason(code)) => {
+                                        routing::Error::try_from(code).ok()
+                                    }
+                                    _ => None,
+                                })
+                                .unwrap_or(routing::Error::None);
+                            return Some(error);
+                        }
+                    }
+                }
+            }
+            None
+        })
+        .await;
+
+        match wait {
+            Ok(Some(routing::Error::None)) => Ok(()),
+            Ok(Some(reason)) => Err(anyhow!("node reported delivery failure: {:?}", reason)),
+            Ok(None) => Err(anyhow!("node closed the connection before acking packet")),
+            Err(_) => Err(anyhow!("timed out waiting for ack on packet {packet_id}")),
+        }
+    }
+}